@@ -1,4 +1,7 @@
 use std::sync::Arc;
+use std::time::Instant;
+
+use cgmath::{Matrix4, Point3, Vector3, Rad, Deg};
 
 use vulkano::instance::{
     Instance,
@@ -25,17 +28,24 @@ use vulkano::swapchain::{
     CompositeAlpha,
     FullscreenExclusive
 };
-use vulkano::image::{SwapchainImage, ImageUsage};
+use vulkano::image::{SwapchainImage, ImmutableImage, AttachmentImage, ImageUsage, Dimensions};
 use vulkano::format::Format;
-use vulkano::sync::SharingMode;
+use vulkano::sync;
+use vulkano::sync::{GpuFuture, FlushError, SharingMode};
 use vulkano::pipeline::viewport::Viewport;
-use vulkano::pipeline::GraphicsPipeline;
-use vulkano::pipeline::vertex::{BufferlessDefinition, BufferlessVertices};
-use vulkano::command_buffer::{DynamicState, AutoCommandBuffer, AutoCommandBufferBuilder, SubpassContents};
+use vulkano::pipeline::{GraphicsPipeline, ComputePipeline, ComputePipelineAbstract};
+use vulkano::pipeline::vertex::SingleBufferDefinition;
+use vulkano::command_buffer::{DynamicState, AutoCommandBuffer, AutoCommandBufferBuilder, CommandBuffer, SubpassContents};
 use vulkano::framebuffer::{RenderPassAbstract, Subpass, FramebufferAbstract, Framebuffer};
 use vulkano::single_pass_renderpass;
+use vulkano::swapchain::{acquire_next_image, AcquireError, SwapchainCreationError};
+use vulkano::buffer::{CpuAccessibleBuffer, CpuBufferPool, DeviceLocalBuffer, BufferUsage};
 use winit::window::Window;
+use winit::event_loop::{EventLoop, ControlFlow};
+use winit::event::{WindowEvent, Event};
 use vulkano::descriptor::PipelineLayoutAbstract;
+use vulkano::descriptor::descriptor_set::{DescriptorSet, PersistentDescriptorSet};
+use vulkano::sampler::{Sampler, Filter, MipmapMode, SamplerAddressMode};
 
 
 const VALIDATION_LAYERS: &[&str] = &[
@@ -48,22 +58,75 @@ const ENABLE_VALIDATION_LAYERS: bool = false;
 #[cfg(not(debug_assertions))]
 const ENABLE_VALIDATION_LAYERS: bool = false;
 
-type ConcreteGraphicsPipeline = GraphicsPipeline<BufferlessDefinition, Box<PipelineLayoutAbstract + Send + Sync + 'static>, Arc<RenderPassAbstract + Send + Sync + 'static>>;
+type ConcreteGraphicsPipeline = GraphicsPipeline<SingleBufferDefinition<Vertex>, Box<PipelineLayoutAbstract + Send + Sync + 'static>, Arc<RenderPassAbstract + Send + Sync + 'static>>;
+type ConcreteParticlePipeline = GraphicsPipeline<SingleBufferDefinition<Particle>, Box<PipelineLayoutAbstract + Send + Sync + 'static>, Arc<RenderPassAbstract + Send + Sync + 'static>>;
+
+const MAX_FRAMES_IN_FLIGHT: usize = 2;
+const TEXTURE_PATH: &str = "src/texture.png";
+const PARTICLE_COUNT: u32 = 1024;
+const PARTICLE_WORKGROUP_SIZE: u32 = 256;
+
+mod particle_compute_shader {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "src/particles.comp"
+    }
+}
+
+#[derive(Default, Debug, Clone)]
+struct Vertex {
+    position: [f32; 2],
+    color: [f32; 3],
+    tex_coord: [f32; 2]
+}
+vulkano::impl_vertex!(Vertex, position, color, tex_coord);
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct UniformBufferObject {
+    model: Matrix4<f32>,
+    view: Matrix4<f32>,
+    proj: Matrix4<f32>
+}
+
+#[derive(Default, Debug, Clone, Copy)]
+struct Particle {
+    position: [f32; 2],
+    velocity: [f32; 2]
+}
+vulkano::impl_vertex!(Particle, position);
 
 #[allow(unused)]
 pub struct App<'a> {
     vulkan_instance: &'a Arc<Instance>,
     debug_callback: Option<DebugCallback>,
     physical_device: PhysicalDevice<'a>,
+    surface: &'a Arc<Surface<Window>>,
     device: Arc<Device>,
     graphics_queue: Arc<Queue>,
     presentation_queue: Arc<Queue>,
     swapchain: Arc<Swapchain<Window>>,
     swapchain_images: Vec<Arc<SwapchainImage<Window>>>,
+    depth_format: Format,
     render_pass: Arc<dyn RenderPassAbstract + Send + Sync>,
     graphics_pipeline: Arc<ConcreteGraphicsPipeline>,
     swapchain_framebuffers: Vec<Arc<dyn FramebufferAbstract + Send + Sync>>,
-    command_buffers: Vec<Arc<AutoCommandBuffer>>
+    vertex_buffer: Arc<CpuAccessibleBuffer<[Vertex]>>,
+    texture: Arc<ImmutableImage<Format>>,
+    sampler: Arc<Sampler>,
+    uniform_buffer_pool: CpuBufferPool<UniformBufferObject>,
+    start_time: Instant,
+    last_frame_time: Instant,
+    compute_pipeline: Arc<dyn ComputePipelineAbstract + Send + Sync>,
+    particle_pipeline: Arc<ConcreteParticlePipeline>,
+    // One buffer per in-flight frame: the compute shader updates particles in
+    // place, so sharing a single buffer would let frame N+1's dispatch race
+    // frame N's draw while it's still reading the same memory.
+    particle_buffers: Vec<Arc<DeviceLocalBuffer<[Particle]>>>,
+    particle_descriptor_sets: Vec<Arc<dyn DescriptorSet + Send + Sync>>,
+    frame_futures: Vec<Option<Box<dyn GpuFuture>>>,
+    current_frame: usize,
+    should_recreate_swapchain: bool
 }
 
 impl<'a> App<'a> {
@@ -78,27 +141,199 @@ impl<'a> App<'a> {
                                    &device,
                                    &graphics_queue,
                                    &presentation_queue);
-        let render_pass = Self::create_render_pass(&device, swapchain.format());
+        let depth_format = Self::select_depth_format(physical_device);
+        let render_pass = Self::create_render_pass(&device, swapchain.format(), depth_format);
         let graphics_pipeline = Self::create_graphics_pipeline(&device, swapchain.dimensions(), &render_pass);
-        let swapchain_framebuffers = Self::create_framebuffers(&swapchain_images, &render_pass);
-        let command_buffers = Self::create_command_buffers(&device, &graphics_queue, &swapchain_framebuffers, &graphics_pipeline);
+        let swapchain_framebuffers = Self::create_framebuffers(&device, &swapchain_images, &render_pass, depth_format);
+        let vertex_buffer = Self::create_vertex_buffer(&device);
+
+        let (texture, texture_future) = Self::create_texture_image(&graphics_queue, TEXTURE_PATH);
+        let sampler = Self::create_sampler(&device);
+        texture_future.then_signal_fence_and_flush().unwrap().wait(None).unwrap();
+
+        let uniform_buffer_pool = CpuBufferPool::<UniformBufferObject>::uniform_buffer(device.clone());
+        let start_time = Instant::now();
+
+        let compute_pipeline = Self::create_compute_pipeline(&device);
+        let particle_pipeline = Self::create_particle_pipeline(&device, swapchain.dimensions(), &render_pass);
+        let particle_buffers: Vec<_> = (0..MAX_FRAMES_IN_FLIGHT)
+            .map(|_| Self::create_particle_buffer(&device, &graphics_queue))
+            .collect();
+        let particle_descriptor_sets = particle_buffers.iter()
+            .map(|particle_buffer| Arc::new(
+                PersistentDescriptorSet::start(
+                    compute_pipeline.descriptor_set_layout(0).unwrap().clone()
+                )
+                    .add_buffer(particle_buffer.clone()).unwrap()
+                    .build().unwrap()
+            ) as Arc<dyn DescriptorSet + Send + Sync>)
+            .collect();
+
+        let frame_futures = (0..MAX_FRAMES_IN_FLIGHT)
+            .map(|_| Some(sync::now(device.clone()).boxed()))
+            .collect();
 
         Self {
             vulkan_instance: &vulkan_instance,
             debug_callback,
             physical_device,
+            surface,
             device,
             graphics_queue,
             presentation_queue,
             swapchain,
             swapchain_images,
+            depth_format,
             render_pass,
             graphics_pipeline,
             swapchain_framebuffers,
-            command_buffers
+            vertex_buffer,
+            texture,
+            sampler,
+            uniform_buffer_pool,
+            start_time,
+            last_frame_time: start_time,
+            compute_pipeline,
+            particle_pipeline,
+            particle_buffers,
+            particle_descriptor_sets,
+            frame_futures,
+            current_frame: 0,
+            should_recreate_swapchain: false
         }
     }
 
+    /// Drives the window's event loop, dispatching resize and redraw events
+    /// into `draw_frame`/`recreate_swapchain`.
+    pub fn run(mut self, event_loop: EventLoop<()>) -> ! {
+        event_loop.run(move |event, _, control_flow| {
+            match event {
+                Event::WindowEvent {
+                    event: WindowEvent::CloseRequested,
+                    ..
+                } => {
+                    *control_flow = ControlFlow::Exit
+                }
+                Event::WindowEvent {
+                    event: WindowEvent::Resized(_),
+                    ..
+                } => {
+                    self.should_recreate_swapchain = true;
+                }
+                Event::RedrawEventsCleared => {
+                    self.draw_frame();
+                }
+                _ => ()
+            }
+        });
+    }
+
+    fn draw_frame(&mut self) {
+        self.frame_futures[self.current_frame].as_mut().unwrap().cleanup_finished();
+
+        if self.should_recreate_swapchain {
+            self.recreate_swapchain();
+        }
+
+        let (image_index, suboptimal, acquire_future) =
+            match acquire_next_image(self.swapchain.clone(), None) {
+                Ok(r) => r,
+                Err(AcquireError::OutOfDate) => {
+                    self.should_recreate_swapchain = true;
+                    return;
+                }
+                Err(e) => panic!("Failed to acquire next image! {:?}", e)
+            };
+
+        if suboptimal {
+            self.should_recreate_swapchain = true;
+        }
+
+        let uniform_buffer = Self::update_uniform_buffer(&self.uniform_buffer_pool, self.swapchain.dimensions(), self.start_time);
+        let descriptor_set = Arc::new(
+            PersistentDescriptorSet::start(
+                self.graphics_pipeline.descriptor_set_layout(0).unwrap().clone()
+            )
+                .add_sampled_image(self.texture.clone(), self.sampler.clone()).unwrap()
+                .add_buffer(uniform_buffer).unwrap()
+                .build().unwrap()
+        ) as Arc<dyn DescriptorSet + Send + Sync>;
+
+        let now = Instant::now();
+        let delta_time = now.duration_since(self.last_frame_time).as_secs_f32();
+        self.last_frame_time = now;
+
+        let compute_command_buffer = Self::create_compute_command_buffer(
+            &self.device,
+            &self.graphics_queue,
+            &self.compute_pipeline,
+            &self.particle_descriptor_sets[self.current_frame],
+            delta_time
+        );
+
+        let command_buffer = Self::create_command_buffer(
+            &self.device,
+            &self.graphics_queue,
+            &self.swapchain_framebuffers[image_index],
+            &self.graphics_pipeline,
+            &self.vertex_buffer,
+            &descriptor_set,
+            &self.particle_pipeline,
+            &self.particle_buffers[self.current_frame]
+        );
+
+        // Chaining both `then_execute` calls on the graphics queue (which also supports
+        // compute) lets vulkano insert the barrier between the particle dispatch and the
+        // draw that reads its output, without needing to build it by hand.
+        let future = self.frame_futures[self.current_frame]
+            .take()
+            .expect("Failed to take!")
+            .join(acquire_future)
+            .then_execute(self.graphics_queue.clone(), compute_command_buffer)
+            .expect("Failed to execute particle dispatch!")
+            .then_execute(self.graphics_queue.clone(), command_buffer)
+            .expect("Failed to execute!")
+            .then_swapchain_present(self.presentation_queue.clone(), self.swapchain.clone(), image_index)
+            .then_signal_fence_and_flush();
+
+        match future {
+            Ok(future) => {
+                self.frame_futures[self.current_frame] = Some(future.boxed());
+            }
+            Err(FlushError::OutOfDate) => {
+                self.should_recreate_swapchain = true;
+                self.frame_futures[self.current_frame] = Some(sync::now(self.device.clone()).boxed());
+            }
+            Err(e) => {
+                println!("Failed to flush future: {:?}", e);
+                self.frame_futures[self.current_frame] = Some(sync::now(self.device.clone()).boxed());
+            }
+        }
+
+        self.current_frame = (self.current_frame + 1) % MAX_FRAMES_IN_FLIGHT;
+    }
+
+    fn recreate_swapchain(&mut self) {
+        let dimensions: [u32; 2] = self.surface.window().inner_size().into();
+        let (new_swapchain, new_images) =
+            match self.swapchain.recreate_with_dimensions(dimensions) {
+                Ok(r) => r,
+                Err(SwapchainCreationError::UnsupportedDimensions) => return,
+                Err(e) => panic!("Failed to recreate swapchain! {:?}", e)
+            };
+
+        self.swapchain = new_swapchain;
+        self.swapchain_images = new_images;
+        self.render_pass = Self::create_render_pass(&self.device, self.swapchain.format(), self.depth_format);
+        self.graphics_pipeline = Self::create_graphics_pipeline(&self.device, self.swapchain.dimensions(), &self.render_pass);
+        self.particle_pipeline = Self::create_particle_pipeline(&self.device, self.swapchain.dimensions(), &self.render_pass);
+        self.swapchain_framebuffers = Self::create_framebuffers(&self.device, &self.swapchain_images, &self.render_pass, self.depth_format);
+        for slot in self.frame_futures.iter_mut() {
+            *slot = Some(sync::now(self.device.clone()).boxed());
+        }
+        self.should_recreate_swapchain = false;
+    }
+
     pub fn create_vulkan_instance() -> Arc<Instance> {
         let validation_layers_supported = Self::check_validation_layer_support();
         if ENABLE_VALIDATION_LAYERS && !validation_layers_supported {
@@ -181,9 +416,9 @@ impl<'a> App<'a> {
 
     fn create_logical_device(instance: &Arc<Instance>, physical_device: PhysicalDevice) -> (Arc<Device>, Arc<Queue>, Arc<Queue>) {
         let queue_family = physical_device.queue_families().find(|queue| {
-            queue.supports_graphics()
+            queue.supports_graphics() && queue.supports_compute()
         })
-        .expect("Couldn't find a graphical queue family!");
+        .expect("Couldn't find a queue family supporting both graphics and compute!");
 
         let queue_priority = 1.0;
         let required_extensions = &Self::get_required_device_extensions(&physical_device);
@@ -203,6 +438,7 @@ impl<'a> App<'a> {
     fn get_required_device_extensions(physical_device: &PhysicalDevice) -> DeviceExtensions {
         DeviceExtensions {
             khr_swapchain: true,
+            khr_storage_buffer_storage_class: true,
             ..DeviceExtensions::none()
         }
     }
@@ -274,7 +510,13 @@ impl<'a> App<'a> {
         surface.window().inner_size().into()
     }
 
-    fn create_render_pass(device: &Arc<Device>, color_format: Format) -> Arc<RenderPassAbstract + Send + Sync> {
+    fn select_depth_format(physical_device: PhysicalDevice) -> Format {
+        [Format::D32Sfloat, Format::D16Unorm].iter().cloned()
+            .find(|format| format.properties(physical_device).optimal_tiling_features.depth_stencil_attachment)
+            .expect("Failed to find a supported depth format!")
+    }
+
+    fn create_render_pass(device: &Arc<Device>, color_format: Format, depth_format: Format) -> Arc<RenderPassAbstract + Send + Sync> {
         Arc::new(single_pass_renderpass!(device.clone(),
             attachments: {
                 color: {
@@ -282,11 +524,17 @@ impl<'a> App<'a> {
                     store: Store,
                     format: color_format,
                     samples: 1,
+                },
+                depth: {
+                    load: Clear,
+                    store: DontCare,
+                    format: depth_format,
+                    samples: 1,
                 }
             },
             pass: {
                 color: [color],
-                depth_stencil: {}
+                depth_stencil: {depth}
             }
         ).expect("Failed to create render pass!"))
     }
@@ -298,14 +546,14 @@ impl<'a> App<'a> {
         mod vertex_shader {
             vulkano_shaders::shader! {
                 ty: "vertex",
-                path: "src/static_triangle.vert"
+                path: "src/textured_triangle.vert"
             }
         }
 
         mod fragment_shader {
             vulkano_shaders::shader! {
                 ty: "fragment",
-                path: "src/vertex_colors.frag"
+                path: "src/textured_triangle.frag"
             }
         }
 
@@ -323,7 +571,7 @@ impl<'a> App<'a> {
         };
 
         Arc::new(GraphicsPipeline::start()
-            .vertex_input(BufferlessDefinition {})
+            .vertex_input_single_buffer()
             .vertex_shader(vert_shader_module.main_entry_point(), ())
             .triangle_list()
             .primitive_restart(false)
@@ -335,57 +583,276 @@ impl<'a> App<'a> {
             .cull_mode_back()
             .front_face_clockwise()
             .blend_pass_through()
+            .depth_stencil_simple_depth()
             .render_pass(Subpass::from(render_pass.clone(), 0).expect("Failed to create subpass!"))
             .build(device.clone())
             .expect("Failed to create graphics pipeline!")
         )
     }
 
-    fn create_framebuffers(swapchain_images: &[Arc<SwapchainImage<Window>>],
-                           render_pass: &Arc<dyn RenderPassAbstract + Send + Sync>
+    fn create_framebuffers(device: &Arc<Device>,
+                           swapchain_images: &[Arc<SwapchainImage<Window>>],
+                           render_pass: &Arc<dyn RenderPassAbstract + Send + Sync>,
+                           depth_format: Format
     ) -> Vec<Arc<dyn FramebufferAbstract + Send + Sync>> {
-
-        let mut dynamic_state = DynamicState {
-            line_width: None,
-            viewports: None,
-            scissors: None,
-            compare_mask: None,
-            write_mask: None,
-            reference: None
-        };
-
+        // Each swapchain image gets its own depth buffer rather than sharing one
+        // across every framebuffer: frames in flight record concurrently, and a
+        // shared depth image would let one frame's depth writes race another's.
         swapchain_images.iter()
             .map(|image| {
+                let depth_buffer = AttachmentImage::transient(device.clone(), image.dimensions(), depth_format)
+                    .expect("Failed to create depth buffer image!");
+
                 Arc::new(Framebuffer::start(render_pass.clone())
                     .add(image.clone()).expect("Failed to add image!")
+                    .add(depth_buffer).expect("Failed to add depth buffer!")
                     .build().expect("Failed to build")
                 ) as Arc<dyn FramebufferAbstract + Send + Sync>
             }
             ).collect::<Vec<_>>()
     }
 
-    fn create_command_buffers(device: &Arc<Device>,
-                              graphics_queue: &Arc<Queue>,
-                              swapchain_framebuffers: &Vec<Arc<dyn FramebufferAbstract + Send + Sync>>,
-                              graphics_pipeline: &Arc<ConcreteGraphicsPipeline>
-    ) -> Vec<Arc<AutoCommandBuffer>> {
+    fn create_vertex_buffer(device: &Arc<Device>) -> Arc<CpuAccessibleBuffer<[Vertex]>> {
+        CpuAccessibleBuffer::from_iter(
+            device.clone(),
+            BufferUsage::all(),
+            false,
+            [
+                Vertex {
+                    position: [0.0, -0.5],
+                    color: [1.0, 0.0, 0.0],
+                    tex_coord: [0.5, 0.0]
+                },
+                Vertex {
+                    position: [-0.5, 0.5],
+                    color: [0.0, 1.0, 0.0],
+                    tex_coord: [0.0, 1.0]
+                },
+                Vertex {
+                    position: [0.5, 0.5],
+                    color: [0.0, 0.0, 1.0],
+                    tex_coord: [1.0, 1.0]
+                }
+            ].iter().cloned()
+        ).expect("Failed to build vertex buffer!")
+    }
+
+    fn create_texture_image(queue: &Arc<Queue>, path: &str) -> (Arc<ImmutableImage<Format>>, Box<dyn GpuFuture>) {
+        let rgba = image::open(path)
+            .expect("Failed to load texture image!")
+            .to_rgba8();
+        let (width, height) = rgba.dimensions();
+
+        let (image, future) = ImmutableImage::from_iter(
+            rgba.into_raw().into_iter(),
+            Dimensions::Dim2d { width, height },
+            Format::R8G8B8A8Srgb,
+            queue.clone()
+        ).expect("Failed to create texture image!");
+
+        (image, future.boxed())
+    }
+
+    fn create_sampler(device: &Arc<Device>) -> Arc<Sampler> {
+        Sampler::new(
+            device.clone(),
+            Filter::Linear,
+            Filter::Linear,
+            MipmapMode::Nearest,
+            SamplerAddressMode::Repeat,
+            SamplerAddressMode::Repeat,
+            SamplerAddressMode::Repeat,
+            0.0, 1.0, 0.0, 0.0
+        ).expect("Failed to create sampler!")
+    }
+
+    fn update_uniform_buffer(
+        uniform_buffer_pool: &CpuBufferPool<UniformBufferObject>,
+        swapchain_extent: [u32; 2],
+        start_time: Instant
+    ) -> vulkano::buffer::cpu_pool::CpuBufferPoolSubbuffer<UniformBufferObject, Arc<vulkano::memory::pool::StdMemoryPool>> {
+        let elapsed = start_time.elapsed().as_secs_f32();
+
+        let mut ubo = UniformBufferObject {
+            model: Matrix4::from_angle_z(Rad::from(Deg(elapsed * 90.0))),
+            view: Matrix4::look_at(
+                Point3::new(2.0, 2.0, 2.0),
+                Point3::new(0.0, 0.0, 0.0),
+                Vector3::new(0.0, 0.0, 1.0)
+            ),
+            proj: cgmath::perspective(
+                Rad::from(Deg(45.0)),
+                swapchain_extent[0] as f32 / swapchain_extent[1] as f32,
+                0.1,
+                10.0
+            )
+        };
+        ubo.proj.y.y *= -1.0;
+
+        uniform_buffer_pool.next(ubo).expect("Failed to build uniform sub-buffer!")
+    }
+
+    fn create_command_buffer(device: &Arc<Device>,
+                             graphics_queue: &Arc<Queue>,
+                             framebuffer: &Arc<dyn FramebufferAbstract + Send + Sync>,
+                             graphics_pipeline: &Arc<ConcreteGraphicsPipeline>,
+                             vertex_buffer: &Arc<CpuAccessibleBuffer<[Vertex]>>,
+                             descriptor_set: &Arc<dyn DescriptorSet + Send + Sync>,
+                             particle_pipeline: &Arc<ConcreteParticlePipeline>,
+                             particle_buffer: &Arc<DeviceLocalBuffer<[Particle]>>
+    ) -> Arc<AutoCommandBuffer> {
         let queue_family = graphics_queue.family();
-        swapchain_framebuffers.iter()
-            .map(|framebuffer| {
-                let vertices = BufferlessVertices { vertices: 3, instances: 1 };
-                let mut builder = AutoCommandBufferBuilder::primary_simultaneous_use(device.clone(), queue_family)
-                    .expect("Failed to create auto command buffer builder");
-                builder
-                    .begin_render_pass(framebuffer.clone(), SubpassContents::Inline, vec![[0.0, 0.0, 0.0, 1.0].into()])
-                    .expect("Failed to begin render pass!")
-                    .draw(graphics_pipeline.clone(), &DynamicState::none(), vertices, (), ())
-                    .expect("Failed to draw!")
-                    .end_render_pass()
-                    .expect("Failed to end render pass!");
-                Arc::new(builder.build()
-                    .expect("Failed to build auto command buffer")
-                )
-            })
-            .collect()
+        let mut builder = AutoCommandBufferBuilder::primary_one_time_submit(device.clone(), queue_family)
+            .expect("Failed to create auto command buffer builder");
+        builder
+            .begin_render_pass(framebuffer.clone(), SubpassContents::Inline, vec![[0.0, 0.0, 0.0, 1.0].into(), 1.0.into()])
+            .expect("Failed to begin render pass!")
+            .draw(graphics_pipeline.clone(), &DynamicState::none(), vertex_buffer.clone(), descriptor_set.clone(), ())
+            .expect("Failed to draw!")
+            .draw(particle_pipeline.clone(), &DynamicState::none(), particle_buffer.clone(), (), ())
+            .expect("Failed to draw particles!")
+            .end_render_pass()
+            .expect("Failed to end render pass!");
+        Arc::new(builder.build()
+            .expect("Failed to build auto command buffer")
+        )
+    }
+
+    fn create_compute_pipeline(device: &Arc<Device>) -> Arc<dyn ComputePipelineAbstract + Send + Sync> {
+        let shader = particle_compute_shader::Shader::load(device.clone())
+            .expect("Failed to create compute shader module!");
+
+        Arc::new(ComputePipeline::new(device.clone(), &shader.main_entry_point(), &(), None)
+            .expect("Failed to create compute pipeline!"))
+    }
+
+    fn create_particle_pipeline(device: &Arc<Device>,
+                                swap_chain_extent: [u32; 2],
+                                render_pass: &Arc<dyn RenderPassAbstract + Send + Sync>
+    ) -> Arc<ConcreteParticlePipeline> {
+        mod vertex_shader {
+            vulkano_shaders::shader! {
+                ty: "vertex",
+                path: "src/particle.vert"
+            }
+        }
+
+        mod fragment_shader {
+            vulkano_shaders::shader! {
+                ty: "fragment",
+                path: "src/particle.frag"
+            }
+        }
+
+        let vert_shader_module = vertex_shader::Shader::load(device.clone())
+            .expect("Failed to create vertex shader module!");
+
+        let frag_shader_module = fragment_shader::Shader::load(device.clone())
+            .expect("Failed to create fragment shader module!");
+
+        let dimensions = [swap_chain_extent[0] as f32, swap_chain_extent[1] as f32];
+        let viewport = Viewport {
+            origin: [0.0, 0.0],
+            dimensions,
+            depth_range: 0.0 .. 1.0
+        };
+
+        Arc::new(GraphicsPipeline::start()
+            .vertex_input_single_buffer()
+            .vertex_shader(vert_shader_module.main_entry_point(), ())
+            .point_list()
+            .viewports(vec![viewport])
+            .fragment_shader(frag_shader_module.main_entry_point(), ())
+            .depth_stencil_simple_depth()
+            .render_pass(Subpass::from(render_pass.clone(), 0).expect("Failed to create subpass!"))
+            .build(device.clone())
+            .expect("Failed to create particle pipeline!")
+        )
+    }
+
+    fn create_particle_buffer(device: &Arc<Device>, graphics_queue: &Arc<Queue>) -> Arc<DeviceLocalBuffer<[Particle]>> {
+        let initial_particles = (0..PARTICLE_COUNT).map(|i| {
+            let angle = (i as f32 / PARTICLE_COUNT as f32) * std::f32::consts::PI * 2.0;
+            Particle {
+                position: [angle.cos() * 0.5, angle.sin() * 0.5],
+                velocity: [angle.cos() * 0.1, angle.sin() * 0.1]
+            }
+        });
+
+        let staging_buffer = CpuAccessibleBuffer::from_iter(
+            device.clone(),
+            BufferUsage::transfer_source(),
+            false,
+            initial_particles
+        ).expect("Failed to build particle staging buffer!");
+
+        let particle_buffer = DeviceLocalBuffer::<[Particle]>::array(
+            device.clone(),
+            PARTICLE_COUNT as usize,
+            BufferUsage {
+                storage_buffer: true,
+                vertex_buffer: true,
+                transfer_destination: true,
+                ..BufferUsage::none()
+            },
+            std::iter::once(graphics_queue.family())
+        ).expect("Failed to build particle buffer!");
+
+        let mut builder = AutoCommandBufferBuilder::primary_one_time_submit(device.clone(), graphics_queue.family())
+            .expect("Failed to create auto command buffer builder");
+        builder.copy_buffer(staging_buffer, particle_buffer.clone())
+            .expect("Failed to record particle buffer upload!");
+
+        builder.build()
+            .expect("Failed to build particle upload command buffer!")
+            .execute(graphics_queue.clone())
+            .expect("Failed to submit particle buffer upload!")
+            .then_signal_fence_and_flush()
+            .expect("Failed to flush particle buffer upload!")
+            .wait(None)
+            .expect("Failed to wait for particle buffer upload!");
+
+        particle_buffer
+    }
+
+    fn create_compute_command_buffer(device: &Arc<Device>,
+                                     queue: &Arc<Queue>,
+                                     compute_pipeline: &Arc<dyn ComputePipelineAbstract + Send + Sync>,
+                                     particle_descriptor_set: &Arc<dyn DescriptorSet + Send + Sync>,
+                                     delta_time: f32
+    ) -> Arc<AutoCommandBuffer> {
+        let push_constants = particle_compute_shader::ty::PushConstants { delta_time };
+        let workgroup_count = particle_workgroup_count(PARTICLE_COUNT, PARTICLE_WORKGROUP_SIZE);
+
+        let mut builder = AutoCommandBufferBuilder::primary_one_time_submit(device.clone(), queue.family())
+            .expect("Failed to create auto command buffer builder");
+        builder.dispatch(
+            [workgroup_count, 1, 1],
+            compute_pipeline.clone(),
+            particle_descriptor_set.clone(),
+            push_constants
+        ).expect("Failed to record particle dispatch!");
+
+        Arc::new(builder.build().expect("Failed to build compute command buffer!"))
+    }
+}
+
+/// Number of `local_size_x = workgroup_size` workgroups needed to cover
+/// `particle_count` invocations, rounding up so the last partial group is
+/// still dispatched (`particles.comp` bounds-checks `gl_GlobalInvocationID.x`).
+fn particle_workgroup_count(particle_count: u32, workgroup_size: u32) -> u32 {
+    (particle_count + workgroup_size - 1) / workgroup_size
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn particle_workgroup_count_rounds_up_to_cover_every_particle() {
+        assert_eq!(particle_workgroup_count(1024, 256), 4);
+        assert_eq!(particle_workgroup_count(1000, 256), 4);
+        assert_eq!(particle_workgroup_count(1, 256), 1);
+        assert_eq!(particle_workgroup_count(0, 256), 0);
     }
 }
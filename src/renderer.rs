@@ -0,0 +1,718 @@
+use std::sync::Arc;
+use std::time::Instant;
+
+use cgmath::{Matrix4, Point3, Vector3, Rad, Deg};
+
+use vulkano::instance::{
+    Instance,
+    InstanceExtensions,
+    ApplicationInfo,
+    Version,
+    layers_list,
+    debug::{
+        DebugCallback,
+        MessageType,
+        MessageSeverity
+    },
+    PhysicalDevice
+};
+use vulkano::device::{Device, DeviceExtensions, Queue};
+use vulkano::swapchain::{
+    Surface,
+    Swapchain,
+    ColorSpace,
+    SupportedPresentModes,
+    PresentMode,
+    SurfaceTransform,
+    CompositeAlpha,
+    FullscreenExclusive,
+    acquire_next_image,
+    AcquireError,
+    SwapchainCreationError
+};
+use vulkano::image::{SwapchainImage, ImmutableImage, AttachmentImage, ImageUsage, Dimensions};
+use vulkano::format::Format;
+use vulkano::sync;
+use vulkano::sync::{GpuFuture, FlushError, SharingMode};
+use vulkano::pipeline::viewport::Viewport;
+use vulkano::pipeline::vertex::SingleBufferDefinition;
+use vulkano::pipeline::GraphicsPipeline;
+use vulkano::command_buffer::{DynamicState, AutoCommandBufferBuilder, SubpassContents};
+use vulkano::framebuffer::{RenderPassAbstract, Subpass, FramebufferAbstract, Framebuffer};
+use vulkano::single_pass_renderpass;
+use winit::window::{Window, WindowBuilder};
+use winit::event_loop::EventLoop;
+use winit::dpi::LogicalSize;
+use vulkano_win::VkSurfaceBuild;
+use vulkano::buffer::{CpuAccessibleBuffer, CpuBufferPool, BufferUsage};
+use vulkano::descriptor::PipelineLayoutAbstract;
+use vulkano::descriptor::descriptor_set::{DescriptorSet, PersistentDescriptorSet};
+use vulkano::sampler::{Sampler, Filter, MipmapMode, SamplerAddressMode};
+
+const VALIDATION_LAYERS: &[&str] = &[
+    "VK_LAYER_KHRONOS_validation"
+];
+
+#[cfg(all(debug_assertions))]
+const ENABLE_VALIDATION_LAYERS: bool = false;
+#[cfg(not(debug_assertions))]
+const ENABLE_VALIDATION_LAYERS: bool = false;
+
+const WIDTH: u32 = 800;
+const HEIGHT: u32 = 600;
+
+const MAX_FRAMES_IN_FLIGHT: usize = 2;
+
+const TEXTURE_PATH: &str = "src/texture.png";
+const MODEL_PATH: &str = "src/model.obj";
+
+type ConcreteGraphicsPipeline = GraphicsPipeline<SingleBufferDefinition<Vertex>, Box<dyn PipelineLayoutAbstract + Send + Sync>, Arc<dyn RenderPassAbstract + Send + Sync>>;
+
+#[derive(Default, Debug, Clone)]
+struct Vertex {
+    position: [f32; 3],
+    normal: [f32; 3],
+    color: [f32; 3],
+    tex_coord: [f32; 2]
+}
+vulkano::impl_vertex!(Vertex, position, normal, color, tex_coord);
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct UniformBufferObject {
+    model: Matrix4<f32>,
+    view: Matrix4<f32>,
+    proj: Matrix4<f32>
+}
+
+/// The concrete device and queues resolved from a given `Surface`, separate
+/// from the instance-level state so a `Renderer` can be rebuilt against a
+/// different window without re-creating the Vulkan instance.
+pub struct SurfaceBinding {
+    pub surface: Arc<Surface<Window>>,
+    pub device: Arc<Device>,
+    pub graphics_queue: Arc<Queue>,
+    pub present_queue: Arc<Queue>,
+}
+
+impl SurfaceBinding {
+    fn new(surface: Arc<Surface<Window>>, physical_device: PhysicalDevice) -> Self {
+        let (device, graphics_queue, present_queue) = create_logical_device(&surface, physical_device);
+        Self { surface, device, graphics_queue, present_queue }
+    }
+}
+
+/// Owns every Vulkan object needed to render and present a frame, so the
+/// event loop only has to dispatch window events into `draw_frame` and
+/// `recreate_swapchain`.
+#[allow(unused)]
+pub struct Renderer<'a> {
+    vulkan_instance: &'a Arc<Instance>,
+    _debug_callback: Option<DebugCallback>,
+    physical_device: PhysicalDevice<'a>,
+    binding: SurfaceBinding,
+    swapchain: Arc<Swapchain<Window>>,
+    render_pass: Arc<dyn RenderPassAbstract + Send + Sync>,
+    graphics_pipeline: Arc<ConcreteGraphicsPipeline>,
+    swapchain_framebuffers: Vec<Arc<dyn FramebufferAbstract + Send + Sync>>,
+    dynamic_state: DynamicState,
+    vertex_buffer: Arc<CpuAccessibleBuffer<[Vertex]>>,
+    index_buffer: Arc<CpuAccessibleBuffer<[u32]>>,
+    texture: Arc<ImmutableImage<Format>>,
+    sampler: Arc<Sampler>,
+    uniform_buffer_pool: CpuBufferPool<UniformBufferObject>,
+    start_time: Instant,
+    frame_futures: Vec<Option<Box<dyn GpuFuture>>>,
+    current_frame: usize,
+    need_to_recreate_swapchain: bool,
+}
+
+impl<'a> Renderer<'a> {
+    pub fn new(vulkan_instance: &'a Arc<Instance>, event_loop: &EventLoop<()>) -> Self {
+        let surface = WindowBuilder::new()
+            .with_title("Vulkan App")
+            .with_inner_size(LogicalSize::new(f64::from(WIDTH), f64::from(HEIGHT)))
+            .build_vk_surface(event_loop, vulkan_instance.clone())
+            .expect("Failed to create window surface!");
+
+        let debug_callback = create_debug_callback(vulkan_instance);
+        let physical_device = select_device(vulkan_instance, &surface);
+        let binding = SurfaceBinding::new(surface, physical_device);
+
+        let (swapchain, swapchain_images) = create_swapchain(
+            &binding.surface,
+            physical_device,
+            &binding.device,
+            &binding.graphics_queue,
+            &binding.present_queue
+        );
+
+        let (vertex_buffer, index_buffer) = create_mesh_buffers(&binding.device, MODEL_PATH);
+        let (texture, texture_future) = create_texture_image(&binding.graphics_queue, TEXTURE_PATH);
+        let sampler = create_sampler(&binding.device);
+        texture_future.then_signal_fence_and_flush().unwrap().wait(None).unwrap();
+
+        let render_pass = create_render_pass(&binding.device, swapchain.format());
+        let graphics_pipeline = create_graphics_pipeline(&binding.device, &render_pass);
+
+        let uniform_buffer_pool = CpuBufferPool::<UniformBufferObject>::uniform_buffer(binding.device.clone());
+        let start_time = Instant::now();
+
+        let mut dynamic_state = DynamicState {
+            line_width: None,
+            viewports: None,
+            scissors: None,
+            compare_mask: None,
+            write_mask: None,
+            reference: None,
+        };
+        let swapchain_framebuffers = create_framebuffers(&binding.device, &swapchain_images, render_pass.clone(), &mut dynamic_state);
+
+        let frame_futures = (0..MAX_FRAMES_IN_FLIGHT)
+            .map(|_| Some(sync::now(binding.device.clone()).boxed()))
+            .collect();
+
+        Self {
+            vulkan_instance,
+            _debug_callback: debug_callback,
+            physical_device,
+            binding,
+            swapchain,
+            render_pass,
+            graphics_pipeline,
+            swapchain_framebuffers,
+            dynamic_state,
+            vertex_buffer,
+            index_buffer,
+            texture,
+            sampler,
+            uniform_buffer_pool,
+            start_time,
+            frame_futures,
+            current_frame: 0,
+            need_to_recreate_swapchain: false,
+        }
+    }
+
+    pub fn window(&self) -> &Window {
+        self.binding.surface.window()
+    }
+
+    pub fn request_resize(&mut self) {
+        self.need_to_recreate_swapchain = true;
+    }
+
+    pub fn recreate_swapchain(&mut self, dimensions: [u32; 2]) {
+        let (new_swapchain, new_images) = match self.swapchain.recreate_with_dimensions(dimensions) {
+            Ok(r) => r,
+            Err(SwapchainCreationError::UnsupportedDimensions) => return,
+            Err(e) => panic!("Failed to recreate swapchain! {:?}", e)
+        };
+        self.swapchain = new_swapchain;
+        self.swapchain_framebuffers = create_framebuffers(&self.binding.device, &new_images, self.render_pass.clone(), &mut self.dynamic_state);
+        self.need_to_recreate_swapchain = false;
+        for slot in self.frame_futures.iter_mut() {
+            *slot = Some(sync::now(self.binding.device.clone()).boxed());
+        }
+    }
+
+    pub fn draw_frame(&mut self) {
+        self.frame_futures[self.current_frame].as_mut().unwrap().cleanup_finished();
+
+        if self.need_to_recreate_swapchain {
+            let dimensions: [u32; 2] = self.window().inner_size().into();
+            self.recreate_swapchain(dimensions);
+        }
+
+        let (image_index, suboptimal, acquire_future) =
+            match acquire_next_image(self.swapchain.clone(), None) {
+                Ok(r) => r,
+                Err(AcquireError::OutOfDate) => {
+                    self.need_to_recreate_swapchain = true;
+                    return;
+                }
+                Err(e) => panic!("Failed to acquire next image! {:?}", e)
+            };
+
+        if suboptimal {
+            println!("Suboptimal image detected, recreating swapchain...");
+            self.need_to_recreate_swapchain = true;
+        }
+
+        let uniform_buffer = update_uniform_buffer(&self.uniform_buffer_pool, self.swapchain.dimensions(), self.start_time);
+        let descriptor_set = Arc::new(
+            PersistentDescriptorSet::start(
+                self.graphics_pipeline.descriptor_set_layout(0).unwrap().clone()
+            )
+                .add_sampled_image(self.texture.clone(), self.sampler.clone()).unwrap()
+                .add_buffer(uniform_buffer).unwrap()
+                .build().unwrap()
+        ) as Arc<dyn DescriptorSet + Send + Sync>;
+
+        let clear_colour = vec![[0.0, 0.0, 0.0, 1.0].into(), 1.0.into()];
+        let mut builder = AutoCommandBufferBuilder::primary_one_time_submit(
+            self.binding.device.clone(),
+            self.binding.graphics_queue.family())
+            .expect("Failed to create auto command buffer builder!");
+        builder
+            .begin_render_pass(
+                self.swapchain_framebuffers[image_index].clone(),
+                SubpassContents::Inline,
+                clear_colour
+            )
+            .expect("Failed to begin render pass!")
+            .draw_indexed(
+                self.graphics_pipeline.clone(),
+                &self.dynamic_state,
+                self.vertex_buffer.clone(),
+                self.index_buffer.clone(),
+                descriptor_set,
+                ()
+            )
+            .expect("Failed to draw!")
+            .end_render_pass()
+            .expect("Failed to end render pass!");
+
+        let command_buffer = builder.build().expect("Failed to build command buffer!");
+
+        let future = self.frame_futures[self.current_frame]
+            .take()
+            .expect("Failed to take!")
+            .join(acquire_future)
+            .then_execute(self.binding.graphics_queue.clone(), command_buffer)
+            .expect("Failed to execute!")
+            .then_swapchain_present(self.binding.present_queue.clone(), self.swapchain.clone(), image_index)
+            .then_signal_fence_and_flush();
+
+        match future {
+            Ok(future) => {
+                self.frame_futures[self.current_frame] = Some(future.boxed());
+            }
+            Err(FlushError::OutOfDate) => {
+                self.need_to_recreate_swapchain = true;
+                self.frame_futures[self.current_frame] = Some(sync::now(self.binding.device.clone()).boxed());
+            }
+            Err(e) => {
+                println!("Failed to flush future: {:?}", e);
+                self.frame_futures[self.current_frame] = Some(sync::now(self.binding.device.clone()).boxed());
+            }
+        }
+
+        self.current_frame = (self.current_frame + 1) % MAX_FRAMES_IN_FLIGHT;
+    }
+}
+
+pub fn create_vulkan_instance() -> Arc<Instance> {
+    let validation_layers_supported = check_validation_layer_support();
+    if ENABLE_VALIDATION_LAYERS && !validation_layers_supported {
+        println!("Validation layers requested but not available!")
+    }
+
+    let supported_extensions = InstanceExtensions::supported_by_core()
+        .expect("Failed to retrieve supported extensions");
+    println!("Supported extensions: {:?}", supported_extensions);
+
+    let app_info = ApplicationInfo {
+        application_name: Some("Vulkan demo".into()),
+        application_version: Some( Version { major: 0, minor: 1, patch: 0}),
+        engine_name: None,
+        engine_version: None
+    };
+
+    let required_extensions = get_required_instance_extensions();
+
+    if ENABLE_VALIDATION_LAYERS && validation_layers_supported {
+        Instance::new(Some(&app_info), &required_extensions, VALIDATION_LAYERS.iter().cloned())
+            .expect("Failed to created Vulkan instance")
+    } else {
+        Instance::new(Some(&app_info), &required_extensions, None)
+            .expect("Failed to created Vulkan instance")
+    }
+}
+
+fn check_validation_layer_support() -> bool {
+    let layers: Vec<_> = layers_list().unwrap().map(|item| item.name().to_owned()).collect();
+    println!("Validation layers supported: {:?}", layers);
+    VALIDATION_LAYERS.iter()
+        .all(|layer_name| layers.contains(&layer_name.to_string()))
+}
+
+fn get_required_instance_extensions() -> InstanceExtensions {
+    let mut required_extensions = vulkano_win::required_extensions();
+    if ENABLE_VALIDATION_LAYERS {
+        required_extensions.ext_debug_utils = true;
+    }
+    required_extensions
+}
+
+fn create_debug_callback(instance: &Arc<Instance>) -> Option<DebugCallback> {
+    if !ENABLE_VALIDATION_LAYERS {
+        return None;
+    }
+
+    let msg_types = MessageType::all();
+    let severity = MessageSeverity {
+        error: true,
+        warning: true,
+        information: true,
+        verbose: true
+    };
+    DebugCallback::new(&instance, severity,msg_types, |msg| {
+        println!("Validation layer: {:?}", msg.description);
+    }).ok()
+}
+
+fn select_device<'a>(instance: &'a Arc<Instance>, surface: &'a Arc<Surface<Window>>) -> PhysicalDevice<'a> {
+    PhysicalDevice::enumerate(&instance)
+        .filter(|device| is_vulkan_compatible(device, &surface))
+        .max_by_key(|device| score_physical_device(device))
+        .map(|device| {
+            println!(
+                "Using device: {} (type: {:?})",
+                device.name(),
+                device.ty()
+            );
+            device
+        })
+        .expect("Failed to find a Vulkan-compatible device")
+}
+
+fn is_vulkan_compatible(device: &PhysicalDevice, surface: &Arc<Surface<Window>>) -> bool {
+    let has_graphics_family = device.queue_families().any(|family| family.supports_graphics());
+    let has_present_family = device.queue_families()
+        .any(|family| surface.is_supported(family).unwrap_or(false));
+    let extensions_supported = DeviceExtensions::supported_by_device(*device).khr_swapchain;
+
+    has_graphics_family && has_present_family && extensions_supported
+}
+
+fn score_physical_device(device: &PhysicalDevice) -> u32 {
+    match device.ty() {
+        vulkano::instance::PhysicalDeviceType::DiscreteGpu => 2,
+        vulkano::instance::PhysicalDeviceType::IntegratedGpu => 1,
+        _ => 0
+    }
+}
+
+fn create_logical_device(surface: &Arc<Surface<Window>>, physical_device: PhysicalDevice) -> (Arc<Device>, Arc<Queue>, Arc<Queue>) {
+    let graphics_family = physical_device.queue_families().find(|family| {
+        family.supports_graphics()
+    })
+        .expect("Couldn't find a graphical queue family!");
+    let present_family = physical_device.queue_families().find(|family| {
+        surface.is_supported(*family).unwrap_or(false)
+    })
+        .expect("Couldn't find a presentation queue family!");
+
+    let queue_priority = 1.0;
+    let required_extensions = &get_required_device_extensions();
+
+    let queue_families = if graphics_family.id() == present_family.id() {
+        vec![(graphics_family, queue_priority)]
+    } else {
+        vec![(graphics_family, queue_priority), (present_family, queue_priority)]
+    };
+
+    let (device, mut queues) = Device::new(
+        physical_device,
+        physical_device.supported_features(),
+        required_extensions,
+        queue_families.into_iter())
+        .expect("Failed to create logical device!");
+
+    let graphics_queue = queues.next().unwrap();
+    let present_queue = queues.next().unwrap_or_else(|| graphics_queue.clone());
+    (device, graphics_queue, present_queue)
+}
+
+fn get_required_device_extensions() -> DeviceExtensions {
+    DeviceExtensions {
+        khr_swapchain: true,
+        khr_storage_buffer_storage_class: true,
+        ..DeviceExtensions::none()
+    }
+}
+
+fn create_swapchain(
+    surface: &Arc<Surface<Window>>,
+    physical_device: PhysicalDevice,
+    logical_device: &Arc<Device>,
+    graphics_queue: &Arc<Queue>,
+    present_queue: &Arc<Queue>
+) -> (Arc<Swapchain<Window>>, Vec<Arc<SwapchainImage<Window>>>) {
+    let capabilities = surface.capabilities(physical_device)
+        .expect("Failed to get capabilities from device");
+    let surface_format = select_swap_surface_format(&capabilities.supported_formats);
+    let present_mode = select_swap_present_mode(capabilities.present_modes);
+
+    let extent: [u32; 2] = surface.window().inner_size().into();
+
+    let sharing_mode: SharingMode = if graphics_queue.family().id() == present_queue.family().id() {
+        graphics_queue.into()
+    } else {
+        vec![graphics_queue, present_queue].as_slice().into()
+    };
+
+    Swapchain::new(
+        logical_device.clone(),
+        surface.clone(),
+        capabilities.min_image_count,
+        surface_format.0,
+        extent,
+        1,
+        ImageUsage::color_attachment(),
+        sharing_mode,
+        SurfaceTransform::Identity,
+        CompositeAlpha::Opaque,
+        present_mode,
+        FullscreenExclusive::Default,
+        true,
+        surface_format.1
+    ).expect("Failed to create swapchain!")
+}
+
+fn select_swap_surface_format(formats: &[(Format, ColorSpace)]) -> (Format, ColorSpace) {
+    *formats.iter().find(|(format, color_space)|
+        *format == Format::B8G8R8A8Srgb && *color_space == ColorSpace::SrgbNonLinear
+    ).unwrap_or_else(|| &formats.first().expect("No surface formats found!"))
+}
+
+fn select_swap_present_mode(available_modes: SupportedPresentModes) -> PresentMode {
+    if available_modes.mailbox {
+        PresentMode::Mailbox
+    } else if available_modes.immediate {
+        PresentMode::Immediate
+    } else {
+        PresentMode::Fifo
+    }
+}
+
+/// Loads a Wavefront `.obj` into a single indexed mesh. `tobj::load_obj(.., true)`
+/// already triangulates and emits one shared index per unique position/normal/
+/// texcoord combination, so `mesh.indices` is used as-is for the index buffer;
+/// there is no further deduplication to do here.
+fn load_mesh(path: &str) -> (Vec<Vertex>, Vec<u32>) {
+    let (models, _materials) = tobj::load_obj(path, true)
+        .expect("Failed to load OBJ file!");
+
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+
+    for model in &models {
+        let mesh = &model.mesh;
+        let index_offset = vertices.len() as u32;
+
+        for index in 0..mesh.positions.len() / 3 {
+            let position = [
+                mesh.positions[3 * index],
+                mesh.positions[3 * index + 1],
+                mesh.positions[3 * index + 2]
+            ];
+            let normal = if mesh.normals.is_empty() {
+                [0.0, 0.0, 0.0]
+            } else {
+                [
+                    mesh.normals[3 * index],
+                    mesh.normals[3 * index + 1],
+                    mesh.normals[3 * index + 2]
+                ]
+            };
+            let tex_coord = if mesh.texcoords.is_empty() {
+                [0.0, 0.0]
+            } else {
+                [mesh.texcoords[2 * index], 1.0 - mesh.texcoords[2 * index + 1]]
+            };
+
+            vertices.push(Vertex {
+                position,
+                normal,
+                color: [1.0, 1.0, 1.0],
+                tex_coord
+            });
+        }
+
+        indices.extend(mesh.indices.iter().map(|&index| index + index_offset));
+    }
+
+    (vertices, indices)
+}
+
+fn create_mesh_buffers(device: &Arc<Device>, path: &str) -> (Arc<CpuAccessibleBuffer<[Vertex]>>, Arc<CpuAccessibleBuffer<[u32]>>) {
+    let (vertices, indices) = load_mesh(path);
+
+    let vertex_buffer = CpuAccessibleBuffer::from_iter(
+        device.clone(),
+        BufferUsage::all(),
+        false,
+        vertices.into_iter()
+    ).expect("Failed to build vertex buffer!");
+
+    let index_buffer = CpuAccessibleBuffer::from_iter(
+        device.clone(),
+        BufferUsage::all(),
+        false,
+        indices.into_iter()
+    ).expect("Failed to build index buffer!");
+
+    (vertex_buffer, index_buffer)
+}
+
+fn create_texture_image(queue: &Arc<Queue>, path: &str) -> (Arc<ImmutableImage<Format>>, Box<dyn GpuFuture>) {
+    let rgba = image::open(path)
+        .expect("Failed to load texture image!")
+        .to_rgba8();
+    let (width, height) = rgba.dimensions();
+
+    let (image, future) = ImmutableImage::from_iter(
+        rgba.into_raw().into_iter(),
+        Dimensions::Dim2d { width, height },
+        Format::R8G8B8A8Srgb,
+        queue.clone()
+    ).expect("Failed to create texture image!");
+
+    (image, future.boxed())
+}
+
+fn create_sampler(device: &Arc<Device>) -> Arc<Sampler> {
+    Sampler::new(
+        device.clone(),
+        Filter::Linear,
+        Filter::Linear,
+        MipmapMode::Nearest,
+        SamplerAddressMode::Repeat,
+        SamplerAddressMode::Repeat,
+        SamplerAddressMode::Repeat,
+        0.0, 1.0, 0.0, 0.0
+    ).expect("Failed to create sampler!")
+}
+
+fn update_uniform_buffer(
+    uniform_buffer_pool: &CpuBufferPool<UniformBufferObject>,
+    swapchain_extent: [u32; 2],
+    start_time: Instant
+) -> vulkano::buffer::cpu_pool::CpuBufferPoolSubbuffer<UniformBufferObject, Arc<vulkano::memory::pool::StdMemoryPool>> {
+    let elapsed = start_time.elapsed().as_secs_f32();
+
+    let mut ubo = UniformBufferObject {
+        model: Matrix4::from_angle_z(Rad::from(Deg(elapsed * 90.0))),
+        view: Matrix4::look_at(
+            Point3::new(2.0, 2.0, 2.0),
+            Point3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, 1.0)
+        ),
+        proj: cgmath::perspective(
+            Rad::from(Deg(45.0)),
+            swapchain_extent[0] as f32 / swapchain_extent[1] as f32,
+            0.1,
+            10.0
+        )
+    };
+    ubo.proj.y.y *= -1.0;
+
+    uniform_buffer_pool.next(ubo).expect("Failed to build uniform sub-buffer!")
+}
+
+fn create_render_pass(device: &Arc<Device>, color_format: Format) -> Arc<dyn RenderPassAbstract + Send + Sync> {
+    Arc::new(single_pass_renderpass!(device.clone(),
+            attachments: {
+                color: {
+                    load: Clear,
+                    store: Store,
+                    format: color_format,
+                    samples: 1,
+                },
+                depth: {
+                    load: Clear,
+                    store: DontCare,
+                    format: Format::D16Unorm,
+                    samples: 1,
+                }
+            },
+            pass: {
+                color: [color],
+                depth_stencil: {depth}
+            }
+        ).expect("Failed to create render pass!"))
+}
+
+fn create_graphics_pipeline(device: &Arc<Device>, render_pass: &Arc<dyn RenderPassAbstract + Send + Sync>) -> Arc<ConcreteGraphicsPipeline> {
+    mod vertex_shader {
+        vulkano_shaders::shader! {
+                ty: "vertex",
+                path: "src/textured.vert"
+            }
+    }
+    mod fragment_shader {
+        vulkano_shaders::shader! {
+                ty: "fragment",
+                path: "src/textured.frag"
+            }
+    }
+
+    let vert_shader_module = vertex_shader::Shader::load(device.clone())
+        .expect("Failed to create vertex shader module!");
+    let frag_shader_module = fragment_shader::Shader::load(device.clone())
+        .expect("Failed to create fragment shader module!");
+
+    Arc::new(GraphicsPipeline::start()
+        .vertex_input_single_buffer()
+        .vertex_shader(vert_shader_module.main_entry_point(), ())
+        .triangle_list()
+        .viewports_dynamic_scissors_irrelevant(1)
+        .fragment_shader(frag_shader_module.main_entry_point(), ())
+        .depth_clamp(false)
+        .cull_mode_back()
+        .blend_pass_through()
+        .depth_stencil_simple_depth()
+        .render_pass(Subpass::from(render_pass.clone(), 0)
+            .expect("Failed to create subpass!"))
+        .build(device.clone())
+        .expect("Failed to create graphics pipeline!")
+    )
+}
+
+fn create_framebuffers(device: &Arc<Device>,
+                       swapchain_images: &[Arc<SwapchainImage<Window>>],
+                       render_pass: Arc<dyn RenderPassAbstract + Send + Sync>,
+                       dynamic_state: &mut DynamicState
+) -> Vec<Arc<dyn FramebufferAbstract + Send + Sync>> {
+    let dimensions = swapchain_images[0].dimensions();
+
+    let viewport = Viewport {
+        origin: [0.0, 0.0],
+        dimensions: [dimensions[0] as f32, dimensions[1] as f32],
+        depth_range: 0.0..1.0
+    };
+    dynamic_state.viewports = Some(vec![viewport]);
+
+    // Each swapchain image gets its own depth buffer rather than sharing one
+    // across every framebuffer: frames in flight record concurrently, and a
+    // shared depth image would let one frame's depth writes race another's.
+    swapchain_images.iter()
+        .map(|image| {
+            let depth_buffer = AttachmentImage::transient(device.clone(), dimensions, Format::D16Unorm)
+                .expect("Failed to create depth buffer image!");
+
+            Arc::new(Framebuffer::start(render_pass.clone())
+                .add(image.clone()).expect("Failed to add image!")
+                .add(depth_buffer).expect("Failed to add depth buffer!")
+                .build().expect("Failed to build")
+            ) as Arc<dyn FramebufferAbstract + Send + Sync>
+        })
+        .collect::<Vec<_>>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_mesh_indexes_every_vertex_in_bounds() {
+        let (vertices, indices) = load_mesh("src/model.obj");
+
+        assert!(!vertices.is_empty());
+        assert!(!indices.is_empty());
+        assert_eq!(indices.len() % 3, 0, "mesh.indices should stay a flat triangle list");
+        assert!(indices.iter().all(|&index| (index as usize) < vertices.len()));
+    }
+}